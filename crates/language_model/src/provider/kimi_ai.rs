@@ -1,12 +1,12 @@
-use anyhow::{anyhow, Result};
-use collections::BTreeMap;
+use anyhow::{anyhow, Context as _, Result};
+use collections::{BTreeMap, HashMap};
 use editor::{Editor, EditorElement, EditorStyle};
-use futures::{future::BoxFuture, FutureExt, StreamExt};
+use futures::{future::BoxFuture, AsyncReadExt, FutureExt, StreamExt};
 use gpui::{
     AnyView, AppContext, AsyncAppContext, FontStyle, ModelContext, Subscription, Task, TextStyle,
     View, WhiteSpace,
 };
-use http_client::HttpClient;
+use http_client::{AsyncBody, HttpClient, Method, Request as HttpRequest};
 use kimi_ai::{
     stream_completion, KimiFunctionDefinition, KimiResponseStreamEvent, KimiToolChoice,
     KimiToolDefinition
@@ -14,7 +14,7 @@ use kimi_ai::{
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use settings::{Settings, SettingsStore};
-use std::{sync::Arc, time::Duration};
+use std::{collections::VecDeque, sync::Arc, time::Duration};
 use strum::IntoEnumIterator;
 use theme::ThemeSettings;
 use ui::{prelude::*, Icon, IconName, Tooltip};
@@ -23,7 +23,8 @@ use util::ResultExt;
 use crate::{
     settings::AllLanguageModelSettings, LanguageModel, LanguageModelId, LanguageModelName,
     LanguageModelProvider, LanguageModelProviderId, LanguageModelProviderName,
-    LanguageModelProviderState, LanguageModelRequest, RateLimiter, Role,
+    LanguageModelProviderState, LanguageModelRequest, LanguageModelRequestMessage,
+    LanguageModelUsage, RateLimiter, Role,
 };
 
 const PROVIDER_ID: &str = "kimiai";
@@ -35,6 +36,7 @@ pub struct KimiAiSettings {
     pub low_speed_timeout: Option<Duration>,
     pub available_models: Vec<KimiAvailableModel>,
     pub needs_setting_migration: bool,
+    pub enable_context_caching: bool,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
@@ -49,12 +51,37 @@ pub struct KimiAiLanguageModelProvider {
     state: gpui::Model<State>,
 }
 
+// Evicted oldest-first once exceeded, so a long session cycling through many system prompts
+// doesn't grow `context_cache_tags` unbounded.
+const MAX_CACHED_CONTEXT_PREFIXES: usize = 64;
+
+const DEFAULT_CONCURRENCY: usize = 4;
+
 pub struct State {
     api_key: Option<String>,
     api_key_from_env: bool,
+    // Cache tags Moonshot has acknowledged for a given message-prefix hash, keyed in insertion
+    // order by `context_cache_order` so the oldest can be evicted once the cap is hit.
+    context_cache_tags: HashMap<u64, Arc<str>>,
+    context_cache_order: VecDeque<u64>,
+    // `TokenCountSource::OfflineFallback` marks a count produced by the offline tiktoken
+    // heuristic because no API key was set or the live estimate call failed, so a later
+    // successful call can overwrite it instead of the inaccurate fallback sticking around for
+    // the life of an unchanged prompt.
+    last_token_count: Option<(u64, usize, TokenCountSource)>,
+    last_usage: Option<LanguageModelUsage>,
+    // Every `KimiAiLanguageModel` reads this fresh from `State` on each call rather than
+    // caching it, so replacing it from `record_usage` takes effect on the very next request.
+    request_limiter: Arc<RateLimiter>,
     _subscription: Subscription,
 }
 
+#[derive(Clone, Copy)]
+enum TokenCountSource {
+    Live,
+    OfflineFallback,
+}
+
 const KIMIAi_API_KEY_VAR: &'static str = "KIMIAI_API_KEY_VAR";
 
 impl State {
@@ -62,6 +89,57 @@ impl State {
         self.api_key.is_some()
     }
 
+    fn context_cache_tag(&self, prefix_hash: u64) -> Option<Arc<str>> {
+        self.context_cache_tags.get(&prefix_hash).cloned()
+    }
+
+    // Stores the cache tag Moonshot assigned after registering `prefix_hash` server-side, so
+    // the next request sharing that prefix can reference it instead of re-registering.
+    fn remember_context_cache_tag(&mut self, prefix_hash: u64, tag: Arc<str>) {
+        if self.context_cache_tags.insert(prefix_hash, tag).is_none() {
+            self.context_cache_order.push_back(prefix_hash);
+        }
+        while self.context_cache_order.len() > MAX_CACHED_CONTEXT_PREFIXES {
+            let Some(oldest) = self.context_cache_order.pop_front() else {
+                break;
+            };
+            self.context_cache_tags.remove(&oldest);
+        }
+    }
+
+    fn cached_token_count(&self, content_hash: u64) -> Option<(usize, TokenCountSource)> {
+        self.last_token_count
+            .and_then(|(hash, count, source)| (hash == content_hash).then_some((count, source)))
+    }
+
+    fn set_cached_token_count(
+        &mut self,
+        content_hash: u64,
+        count: usize,
+        source: TokenCountSource,
+    ) {
+        self.last_token_count = Some((content_hash, count, source));
+    }
+
+    // Sizes `request_limiter` off the completion stream's own usage rather than a different
+    // endpoint's quota: large completions burn through Moonshot's per-minute budget faster, so
+    // fewer requests should be allowed in flight at once. Replacing (rather than mutating) the
+    // limiter means models that read it fresh on their next call pick up the new cap, while
+    // ones already mid-request keep running against the one they started with.
+    fn record_usage(&mut self, usage: LanguageModelUsage) {
+        let concurrency = match usage.output_tokens {
+            tokens if tokens > 4_000 => 2,
+            tokens if tokens > 1_000 => 3,
+            _ => DEFAULT_CONCURRENCY,
+        };
+        self.request_limiter = Arc::new(RateLimiter::new(concurrency));
+        self.last_usage = Some(usage);
+    }
+
+    fn last_usage(&self) -> Option<LanguageModelUsage> {
+        self.last_usage.clone()
+    }
+
     fn reset_api_key(&self, cx: &mut ModelContext<Self>) -> Task<Result<()>> {
         let settings = &AllLanguageModelSettings::get_global(cx).kimiai;
         let delete_credentials = cx.delete_credentials(&settings.api_url);
@@ -122,6 +200,11 @@ impl KimiAiLanguageModelProvider {
         let state = cx.new_model(|cx| State {
             api_key: None,
             api_key_from_env: false,
+            context_cache_tags: HashMap::default(),
+            context_cache_order: VecDeque::default(),
+            last_token_count: None,
+            last_usage: None,
+            request_limiter: Arc::new(RateLimiter::new(DEFAULT_CONCURRENCY)),
             _subscription: cx.observe_global::<SettingsStore>(|_this: &mut State, cx| {
                 cx.notify();
             }),
@@ -185,7 +268,6 @@ impl LanguageModelProvider for KimiAiLanguageModelProvider {
                     model,
                     state: self.state.clone(),
                     http_client: self.http_client.clone(),
-                    request_limiter: RateLimiter::new(4),
                 }) as Arc<dyn LanguageModel>
             })
             .collect()
@@ -214,7 +296,6 @@ pub struct KimiAiLanguageModel {
     model: kimi_ai::Model,
     state: gpui::Model<State>,
     http_client: Arc<dyn HttpClient>,
-    request_limiter: RateLimiter,
 }
 
 impl KimiAiLanguageModel {
@@ -227,18 +308,24 @@ impl KimiAiLanguageModel {
         Result<futures::stream::BoxStream<'static, Result<KimiResponseStreamEvent>>>,
     > {
         let http_client = self.http_client.clone();
-        let Ok((api_key, api_url, low_speed_timeout)) = cx.read_model(&self.state, |state, cx| {
-            let settings = &AllLanguageModelSettings::get_global(cx).kimiai;
-            (
-                state.api_key.clone(),
-                settings.api_url.clone(),
-                settings.low_speed_timeout,
-            )
-        }) else {
+        // Read the limiter fresh on every call rather than caching it on `self`, so a resize
+        // State applies after this model was handed out (e.g. from `record_usage`) takes effect
+        // on the very next request instead of only on ones built after the resize.
+        let Ok((api_key, api_url, low_speed_timeout, request_limiter)) =
+            cx.read_model(&self.state, |state, cx| {
+                let settings = &AllLanguageModelSettings::get_global(cx).kimiai;
+                (
+                    state.api_key.clone(),
+                    settings.api_url.clone(),
+                    settings.low_speed_timeout,
+                    state.request_limiter.clone(),
+                )
+            })
+        else {
             return futures::future::ready(Err(anyhow!("App state dropped"))).boxed();
         };
 
-        let future = self.request_limiter.stream(async move {
+        let future = request_limiter.stream(async move {
             let api_key = api_key.ok_or_else(|| anyhow!("missing api key"))?;
             let request = stream_completion(
                 http_client.as_ref(),
@@ -253,6 +340,173 @@ impl KimiAiLanguageModel {
 
         async move { Ok(future.await?.boxed()) }.boxed()
     }
+
+    // Reads the current shared limiter out of `State` rather than caching it on `self`, so a
+    // resize applied after this model was handed out takes effect on the next request.
+    fn current_request_limiter(&self, cx: &AsyncAppContext) -> Result<Arc<RateLimiter>> {
+        cx.read_model(&self.state, |state, _cx| state.request_limiter.clone())
+            .map_err(|_| anyhow!("App state dropped"))
+    }
+
+    // Looks up the cache tag Moonshot has already acknowledged for this request's stable
+    // message prefix. `should_register` is set when caching is enabled but no tag is known yet,
+    // so the request can ask Moonshot to register one instead of sending an invented tag.
+    fn context_cache_lookup(
+        &self,
+        messages: &[LanguageModelRequestMessage],
+        cx: &AsyncAppContext,
+    ) -> ContextCacheLookup {
+        let Some(prefix_hash) = hash_message_prefix(messages) else {
+            return ContextCacheLookup::default();
+        };
+
+        let (enabled, tag) = cx
+            .read_model(&self.state, |state, cx| {
+                (
+                    AllLanguageModelSettings::get_global(cx)
+                        .kimiai
+                        .enable_context_caching,
+                    state.context_cache_tag(prefix_hash),
+                )
+            })
+            .unwrap_or((false, None));
+
+        ContextCacheLookup {
+            prefix_hash: Some(prefix_hash),
+            should_register: enabled && tag.is_none(),
+            tag: enabled.then_some(tag).flatten(),
+        }
+    }
+
+    pub fn use_partial_prefill(
+        &self,
+        request: LanguageModelRequest,
+        prefix: String,
+        cx: &AsyncAppContext,
+    ) -> BoxFuture<'static, Result<futures::stream::BoxStream<'static, Result<String>>>> {
+        let cache = self.context_cache_lookup(&request.messages, cx);
+        let prefix_hash = cache.prefix_hash;
+        let mut request = request.into_kimi_ai(self.model.id().into(), self.max_output_tokens());
+        request.cache_tag = cache.tag;
+        request.register_context_cache = cache.should_register;
+        request.messages.push(kimi_ai::RequestMessage::Assistant {
+            content: Some(prefix),
+            partial: true,
+        });
+
+        let request_limiter = match self.current_request_limiter(cx) {
+            Ok(request_limiter) => request_limiter,
+            Err(error) => return futures::future::ready(Err(error)).boxed(),
+        };
+        let response = self.stream_completion(request, cx);
+        let state = self.state.clone();
+        let cx = cx.clone();
+        request_limiter
+            .run(async move {
+                let events =
+                    record_stream_side_effects(response.await?, state, cx, prefix_hash, None);
+                Ok(kimi_ai::extract_text_from_events(events).boxed())
+            })
+            .boxed()
+    }
+
+    /// Like `stream_completion`, but also resolves a `LanguageModelUsage` once Kimi reports it
+    /// on the terminal chunk of the stream, so callers that need exact token counts for cost
+    /// reporting (rather than the offline/estimate counts from `count_tokens`) can await it
+    /// alongside the text.
+    pub fn stream_completion_with_usage(
+        &self,
+        request: LanguageModelRequest,
+        cx: &AsyncAppContext,
+    ) -> BoxFuture<
+        'static,
+        Result<(
+            futures::stream::BoxStream<'static, Result<String>>,
+            futures::channel::oneshot::Receiver<LanguageModelUsage>,
+        )>,
+    > {
+        let cache = self.context_cache_lookup(&request.messages, cx);
+        let prefix_hash = cache.prefix_hash;
+        let mut request = request.into_kimi_ai(self.model.id().into(), self.max_output_tokens());
+        request.cache_tag = cache.tag;
+        request.register_context_cache = cache.should_register;
+
+        let response = self.stream_completion(request, cx);
+        let state = self.state.clone();
+        let cx = cx.clone();
+        async move {
+            let (usage_tx, usage_rx) = futures::channel::oneshot::channel();
+            let events =
+                record_stream_side_effects(response.await?, state, cx, prefix_hash, Some(usage_tx));
+            Ok((kimi_ai::extract_text_from_events(events).boxed(), usage_rx))
+        }
+        .boxed()
+    }
+}
+
+#[derive(Default)]
+struct ContextCacheLookup {
+    prefix_hash: Option<u64>,
+    tag: Option<Arc<str>>,
+    should_register: bool,
+}
+
+// Taps a raw event stream for the usage Kimi reports on its terminal chunk and the cache tag
+// Moonshot assigns the first time a message prefix is registered for context caching. Usage is
+// recorded on `State` (and used to live-resize `request_limiter`) for every stream; `usage_tx`
+// additionally forwards it to a specific caller when one asked for it via
+// `stream_completion_with_usage`.
+fn record_stream_side_effects(
+    events: futures::stream::BoxStream<'static, Result<KimiResponseStreamEvent>>,
+    state: gpui::Model<State>,
+    cx: AsyncAppContext,
+    prefix_hash: Option<u64>,
+    usage_tx: Option<futures::channel::oneshot::Sender<LanguageModelUsage>>,
+) -> futures::stream::BoxStream<'static, Result<KimiResponseStreamEvent>> {
+    let mut usage_tx = usage_tx;
+    events
+        .inspect(move |event| {
+            let Ok(event) = event else { return };
+            if let Some(usage) = event.usage.clone() {
+                let usage = convert_kimi_usage(usage);
+                cx.update_model(&state, |state, _cx| state.record_usage(usage.clone()))
+                    .ok();
+                if let Some(tx) = usage_tx.take() {
+                    tx.send(usage).ok();
+                }
+            }
+            if let (Some(prefix_hash), Some(tag)) = (prefix_hash, event.context_cache_tag.clone())
+            {
+                cx.update_model(&state, |state, _cx| {
+                    state.remember_context_cache_tag(prefix_hash, tag)
+                })
+                .ok();
+            }
+        })
+        .boxed()
+}
+
+fn convert_kimi_usage(usage: kimi_ai::Usage) -> LanguageModelUsage {
+    LanguageModelUsage {
+        input_tokens: usage.prompt_tokens as u32,
+        output_tokens: usage.completion_tokens as u32,
+    }
+}
+
+fn hash_message_prefix(messages: &[LanguageModelRequestMessage]) -> Option<u64> {
+    use std::hash::{Hash, Hasher};
+
+    let mut prefix = messages
+        .iter()
+        .take_while(|message| matches!(message.role, Role::System))
+        .peekable();
+    prefix.peek()?;
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for message in prefix {
+        message.string_contents().hash(&mut hasher);
+    }
+    Some(hasher.finish())
 }
 
 impl LanguageModel for KimiAiLanguageModel {
@@ -287,16 +541,68 @@ impl LanguageModel for KimiAiLanguageModel {
     fn count_tokens(
         &self,
         request: LanguageModelRequest,
-        _cx: &AppContext,
+        cx: &AppContext,
     ) -> BoxFuture<'static, Result<usize>> {
-        // count_kimi_ai_tokens(request, self.model.clone(), cx)
-        let token_count = request
-            .messages
-            .iter()
-            .map(|msg| msg.string_contents().chars().count())
-            .sum::<usize>()
-            *2;
-        async move { Ok(token_count)}.boxed()
+        let http_client = self.http_client.clone();
+        let model = self.model.clone();
+        let content_hash = hash_messages(&request.messages);
+        let state = self.state.clone();
+        let async_cx = cx.to_async();
+        let (api_key, api_url, cached) = {
+            let state = state.read(cx);
+            let settings = &AllLanguageModelSettings::get_global(cx).kimiai;
+            (
+                state.api_key.clone(),
+                settings.api_url.clone(),
+                state.cached_token_count(content_hash),
+            )
+        };
+
+        cx.background_executor()
+            .spawn(async move {
+                // A cached live count is trustworthy as-is; a cached fallback count is only a
+                // stand-in for a live count, so retry the live call (when an API key is set)
+                // before falling back to it again.
+                if let Some((count, TokenCountSource::Live)) = cached {
+                    return Ok(count);
+                }
+
+                let (total_tokens, source) = match &api_key {
+                    Some(api_key) => match estimate_kimi_ai_tokens(
+                        http_client.as_ref(),
+                        &api_url,
+                        api_key,
+                        model.id(),
+                        &request,
+                    )
+                    .await
+                    {
+                        Ok(total_tokens) => (total_tokens, TokenCountSource::Live),
+                        Err(_) => match cached {
+                            Some((count, TokenCountSource::OfflineFallback)) => {
+                                (count, TokenCountSource::OfflineFallback)
+                            }
+                            _ => (
+                                count_kimi_ai_tokens_offline(&request, &model)?,
+                                TokenCountSource::OfflineFallback,
+                            ),
+                        },
+                    },
+                    None => (
+                        count_kimi_ai_tokens_offline(&request, &model)?,
+                        TokenCountSource::OfflineFallback,
+                    ),
+                };
+
+                async_cx
+                    .update_model(&state, |state, _cx| {
+                        state.set_cached_token_count(content_hash, total_tokens, source)
+                    })
+                    .ok();
+
+                Ok(total_tokens)
+            })
+            .boxed()
     }
 
     fn stream_completion(
@@ -304,9 +610,21 @@ impl LanguageModel for KimiAiLanguageModel {
         request: LanguageModelRequest,
         cx: &AsyncAppContext,
     ) -> BoxFuture<'static, Result<futures::stream::BoxStream<'static, Result<String>>>> {
-        let request = request.into_kimi_ai(self.model.id().into(), self.max_output_tokens());
+        let cache = self.context_cache_lookup(&request.messages, cx);
+        let prefix_hash = cache.prefix_hash;
+        let mut request = request.into_kimi_ai(self.model.id().into(), self.max_output_tokens());
+        request.cache_tag = cache.tag;
+        request.register_context_cache = cache.should_register;
+
         let completions = self.stream_completion(request, cx);
-        async move { Ok(kimi_ai::extract_text_from_events(completions.await?).boxed()) }.boxed()
+        let state = self.state.clone();
+        let cx = cx.clone();
+        async move {
+            let events =
+                record_stream_side_effects(completions.await?, state, cx, prefix_hash, None);
+            Ok(kimi_ai::extract_text_from_events(events).boxed())
+        }
+        .boxed()
     }
 
     fn use_any_tool(
@@ -317,7 +635,11 @@ impl LanguageModel for KimiAiLanguageModel {
         schema: serde_json::Value,
         cx: &AsyncAppContext,
     ) -> BoxFuture<'static, Result<futures::stream::BoxStream<'static, Result<String>>>> {
+        let cache = self.context_cache_lookup(&request.messages, cx);
+        let prefix_hash = cache.prefix_hash;
         let mut request = request.into_kimi_ai(self.model.id().into(), self.max_output_tokens());
+        request.cache_tag = cache.tag;
+        request.register_context_cache = cache.should_register;
         request.tool_choice = Some(KimiToolChoice::Other(KimiToolDefinition::Function {
             function: KimiFunctionDefinition {
                 name: tool_name.clone(),
@@ -333,12 +655,19 @@ impl LanguageModel for KimiAiLanguageModel {
             },
         }];
 
+        let request_limiter = match self.current_request_limiter(cx) {
+            Ok(request_limiter) => request_limiter,
+            Err(error) => return futures::future::ready(Err(error)).boxed(),
+        };
         let response = self.stream_completion(request, cx);
-        self.request_limiter
+        let state = self.state.clone();
+        let cx = cx.clone();
+        request_limiter
             .run(async move {
-                let response = response.await?;
+                let events =
+                    record_stream_side_effects(response.await?, state, cx, prefix_hash, None);
                 Ok(
-                    kimi_ai::extract_tool_args_from_events(tool_name, Box::pin(response))
+                    kimi_ai::extract_tool_args_from_events(tool_name, Box::pin(events))
                         .await?
                         .boxed(),
                 )
@@ -347,37 +676,117 @@ impl LanguageModel for KimiAiLanguageModel {
     }
 }
 
+fn hash_messages(messages: &[LanguageModelRequestMessage]) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for message in messages {
+        message.string_contents().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+fn count_kimi_ai_tokens_offline(
+    request: &LanguageModelRequest,
+    _model: &kimi_ai::Model,
+) -> Result<usize> {
+    let messages = request
+        .messages
+        .iter()
+        .map(|message| tiktoken_rs::ChatCompletionRequestMessage {
+            role: match message.role {
+                Role::User => "user".into(),
+                Role::Assistant => "assistant".into(),
+                Role::System => "system".into(),
+            },
+            content: Some(message.string_contents()),
+            name: None,
+            function_call: None,
+        })
+        .collect::<Vec<_>>();
+
+    tiktoken_rs::num_tokens_from_messages("gpt-4", &messages)
+}
+
 pub fn count_kimi_ai_tokens(
     request: LanguageModelRequest,
     model: kimi_ai::Model,
     cx: &AppContext,
 ) -> BoxFuture<'static, Result<usize>> {
     cx.background_executor()
-        .spawn(async move {
-            let messages = request
-                .messages
-                .into_iter()
-                .map(|message| tiktoken_rs::ChatCompletionRequestMessage {
-                    role: match message.role {
-                        Role::User => "user".into(),
-                        Role::Assistant => "assistant".into(),
-                        Role::System => "system".into(),
-                    },
-                    content: Some(message.string_contents()),
-                    name: None,
-                    function_call: None,
-                })
-                .collect::<Vec<_>>();
-
-            if let kimi_ai::Model::Custom { .. } = model {
-                tiktoken_rs::num_tokens_from_messages("gpt-4", &messages)
-            } else {
-                tiktoken_rs::num_tokens_from_messages("gpt-4", &messages)
-            }
-        })
+        .spawn(async move { count_kimi_ai_tokens_offline(&request, &model) })
         .boxed()
 }
 
+#[derive(Serialize)]
+struct EstimateTokenCountBody {
+    model: String,
+    messages: Vec<EstimateTokenCountMessage>,
+}
+
+#[derive(Serialize)]
+struct EstimateTokenCountMessage {
+    role: &'static str,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct EstimateTokenCountResponse {
+    data: EstimateTokenCountData,
+}
+
+#[derive(Deserialize)]
+struct EstimateTokenCountData {
+    total_tokens: usize,
+}
+
+async fn estimate_kimi_ai_tokens(
+    http_client: &dyn HttpClient,
+    api_url: &str,
+    api_key: &str,
+    model_id: &str,
+    request: &LanguageModelRequest,
+) -> Result<usize> {
+    let uri = format!("{api_url}/tokenizers/estimate-token-count");
+    let body = EstimateTokenCountBody {
+        model: model_id.to_string(),
+        messages: request
+            .messages
+            .iter()
+            .map(|message| EstimateTokenCountMessage {
+                role: match message.role {
+                    Role::User => "user",
+                    Role::Assistant => "assistant",
+                    Role::System => "system",
+                },
+                content: message.string_contents(),
+            })
+            .collect(),
+    };
+
+    let request = HttpRequest::builder()
+        .method(Method::POST)
+        .uri(uri)
+        .header("Content-Type", "application/json")
+        .header("Authorization", format!("Bearer {api_key}"))
+        .body(AsyncBody::from(serde_json::to_string(&body)?))?;
+
+    let mut response = http_client.send(request).await?;
+    let mut body = String::new();
+    response.body_mut().read_to_string(&mut body).await?;
+
+    if response.status().is_success() {
+        let response: EstimateTokenCountResponse = serde_json::from_str(&body)
+            .with_context(|| format!("failed to parse estimate-token-count response: {body}"))?;
+        Ok(response.data.total_tokens)
+    } else {
+        Err(anyhow!(
+            "estimate-token-count request failed with status {}: {body}",
+            response.status()
+        ))
+    }
+}
+
 struct ConfigurationView {
     api_key_editor: View<Editor>,
     state: gpui::Model<State>,
@@ -499,6 +908,7 @@ impl Render for ConfigurationView {
         ];
 
         let env_var_set = self.state.read(cx).api_key_from_env;
+        let last_usage = self.state.read(cx).last_usage();
 
         if self.load_credentials_task.is_some() {
             div().child(Label::new("Loading credentials...")).into_any()
@@ -527,30 +937,41 @@ impl Render for ConfigurationView {
                 )
                 .into_any()
         } else {
-            h_flex()
+            v_flex()
                 .size_full()
-                .justify_between()
                 .child(
                     h_flex()
-                        .gap_1()
-                        .child(Icon::new(IconName::Check).color(Color::Success))
-                        .child(Label::new(if env_var_set {
-                            format!("API key set in {KIMIAi_API_KEY_VAR} environment variable.")
-                        } else {
-                            "API key configured.".to_string()
-                        })),
-                )
-                .child(
-                    Button::new("reset-key", "Reset key")
-                        .icon(Some(IconName::Trash))
-                        .icon_size(IconSize::Small)
-                        .icon_position(IconPosition::Start)
-                        .disabled(env_var_set)
-                        .when(env_var_set, |this| {
-                            this.tooltip(|cx| Tooltip::text(format!("To reset your API key, unset the {KIMIAi_API_KEY_VAR} environment variable."), cx))
-                        })
-                        .on_click(cx.listener(|this, _, cx| this.reset_api_key(cx))),
+                        .size_full()
+                        .justify_between()
+                        .child(
+                            h_flex()
+                                .gap_1()
+                                .child(Icon::new(IconName::Check).color(Color::Success))
+                                .child(Label::new(if env_var_set {
+                                    format!("API key set in {KIMIAi_API_KEY_VAR} environment variable.")
+                                } else {
+                                    "API key configured.".to_string()
+                                })),
+                        )
+                        .child(
+                            Button::new("reset-key", "Reset key")
+                                .icon(Some(IconName::Trash))
+                                .icon_size(IconSize::Small)
+                                .icon_position(IconPosition::Start)
+                                .disabled(env_var_set)
+                                .when(env_var_set, |this| {
+                                    this.tooltip(|cx| Tooltip::text(format!("To reset your API key, unset the {KIMIAi_API_KEY_VAR} environment variable."), cx))
+                                })
+                                .on_click(cx.listener(|this, _, cx| this.reset_api_key(cx))),
+                        ),
                 )
+                .children(last_usage.map(|usage| {
+                    Label::new(format!(
+                        "Last request used {} input / {} output tokens.",
+                        usage.input_tokens, usage.output_tokens
+                    ))
+                    .size(LabelSize::Small)
+                }))
                 .into_any()
         }
     }